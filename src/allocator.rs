@@ -1,11 +1,7 @@
+use crate::oncelock::OnceLock;
 use crate::QueueStructure;
 use alloc::boxed::Box;
 use crossbeam_queue::ArrayQueue;
-#[cfg(feature = "std")]
-use std::sync::OnceLock;
-
-#[cfg(not(feature = "std"))]
-use once_cell::sync::OnceCell as OnceLock;
 
 const QUEUE_POOL_CAPACITY: usize = 128;
 
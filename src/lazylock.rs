@@ -0,0 +1,166 @@
+/// A value which is lazily initialized on first access.
+///
+/// Layered on top of [`OnceLock`](crate::oncelock::OnceLock): the completion
+/// state lives in an `OnceLock<()>`, which only ever stores a zero-sized
+/// value, while the initializing closure and the resulting value share a
+/// single union slot discriminated by that state. This keeps a `LazyLock<T>`
+/// no larger than it needs to be instead of storing `F` and `T` side by
+/// side.
+///
+/// Unlike the `OnceLock` underneath it, `LazyLock` cannot allow `F` to be
+/// retried after it panics: `F` is taken out of the union before it runs, so
+/// a second attempt (by a waiter that gets woken once `once` rolls back to
+/// `EMPTY`, or by this same cell being forced again later) would read the
+/// same moved-from bytes a second time. So a panicking `F` poisons the cell
+/// instead, matching `std::sync::Once`'s poisoning behavior.
+use crate::oncelock::OnceLock;
+use core::cell::UnsafeCell;
+use core::mem::ManuallyDrop;
+use core::ops::Deref;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+union Data<T, F> {
+    value: ManuallyDrop<T>,
+    init: ManuallyDrop<F>,
+}
+
+pub(crate) struct LazyLock<T, F = fn() -> T> {
+    once: OnceLock<()>,
+    poisoned: AtomicBool,
+    data: UnsafeCell<Data<T, F>>,
+}
+
+unsafe impl<T: Send + Sync, F: Send> Sync for LazyLock<T, F> {}
+unsafe impl<T: Send, F: Send> Send for LazyLock<T, F> {}
+
+impl<T, F> LazyLock<T, F>
+where
+    F: FnOnce() -> T,
+{
+    pub const fn new(f: F) -> Self {
+        Self {
+            once: OnceLock::new(),
+            poisoned: AtomicBool::new(false),
+            data: UnsafeCell::new(Data {
+                init: ManuallyDrop::new(f),
+            }),
+        }
+    }
+
+    /// Forces evaluation of the closure, returning the resulting value.
+    ///
+    /// Repeated calls return the same value without re-running `F`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a previous call to `force` panicked partway through `F`.
+    pub fn force(&self) -> &T {
+        self.once.get_or_init(|| {
+            assert!(
+                !self.poisoned.load(Ordering::Acquire),
+                "LazyLock instance has previously been poisoned"
+            );
+
+            // Pessimistically mark the cell poisoned before running `F`, and
+            // only clear it once `value` has actually been written. If `F`
+            // unwinds, this store is the only trace left behind: `once`
+            // itself rolls back to `EMPTY` (see the panic-safety and parking
+            // commits) and would otherwise hand `init`'s already-moved-from
+            // bytes to whichever thread retries next.
+            self.poisoned.store(true, Ordering::Release);
+
+            // SAFETY: `get_or_init` only runs this closure once per `once`
+            // generation, before `once` is marked initialized, so `data`
+            // still holds `init` and no other call can be observing `value`
+            // yet.
+            let init = unsafe { ManuallyDrop::take(&mut (*self.data.get()).init) };
+            let value = init();
+            unsafe {
+                (*self.data.get()).value = ManuallyDrop::new(value);
+            }
+
+            self.poisoned.store(false, Ordering::Release);
+        });
+        // SAFETY: the `OnceLock` is now initialized, so `data` holds `value`.
+        unsafe { &(*self.data.get()).value }
+    }
+}
+
+impl<T, F> Deref for LazyLock<T, F>
+where
+    F: FnOnce() -> T,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.force()
+    }
+}
+
+impl<T, F> Drop for LazyLock<T, F> {
+    fn drop(&mut self) {
+        if *self.poisoned.get_mut() {
+            // `F` panicked partway through `force`: `init` was already
+            // moved out of the union and `value` was never written, so
+            // neither field holds a value that is safe to drop.
+            return;
+        }
+        // SAFETY: `once.get()` tells us which field of the union is live.
+        unsafe {
+            if self.once.get().is_some() {
+                ManuallyDrop::drop(&mut (*self.data.get()).value);
+            } else {
+                ManuallyDrop::drop(&mut (*self.data.get()).init);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn force_after_a_panic_is_poisoned_instead_of_retrying() {
+        let lazy: LazyLock<u32, _> = LazyLock::new(|| panic!("init failed"));
+
+        let first = panic::catch_unwind(AssertUnwindSafe(|| lazy.force()));
+        assert!(first.is_err());
+
+        // A second attempt must not re-take the already-moved-from `init`;
+        // it should refuse with the poison message instead.
+        let second = panic::catch_unwind(AssertUnwindSafe(|| lazy.force()));
+        let message = second.unwrap_err();
+        let message = message
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| message.downcast_ref::<std::string::String>().map(|s| s.as_str()));
+        assert_eq!(message, Some("LazyLock instance has previously been poisoned"));
+    }
+
+    #[test]
+    fn value_is_dropped_exactly_once_on_the_happy_path() {
+        struct CountDrops<'a>(&'a AtomicUsize);
+        impl Drop for CountDrops<'_> {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let drops = AtomicUsize::new(0);
+        {
+            let lazy = LazyLock::new(|| CountDrops(&drops));
+            // Force more than once: the closure must run exactly once and
+            // every call must observe the same value.
+            let first = lazy.force() as *const _;
+            let second = lazy.force() as *const _;
+            assert_eq!(first, second);
+            assert_eq!(drops.load(Ordering::Relaxed), 0);
+        }
+        assert_eq!(drops.load(Ordering::Relaxed), 1);
+    }
+}
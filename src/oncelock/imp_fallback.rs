@@ -0,0 +1,241 @@
+//! `no_std` fallback backend: a pure spin/backoff wait loop with no OS-level
+//! synchronization primitives. Used whenever the `std` feature is disabled.
+use crate::backoff::Backoff;
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+const EMPTY: u8 = 0;
+const INITIALIZING: u8 = 1;
+const INITIALIZED: u8 = 2;
+
+/// Resets `state` back to `EMPTY` on drop.
+///
+/// Armed around the call to the user-supplied initializer and defused as
+/// soon as that call returns, so that an unwinding panic
+/// leaves the cell free for another thread to retry instead of stuck at
+/// `INITIALIZING` forever.
+#[cfg(panic = "unwind")]
+struct ResetOnUnwind<'a> {
+    state: &'a AtomicU8,
+}
+
+#[cfg(panic = "unwind")]
+impl Drop for ResetOnUnwind<'_> {
+    #[inline]
+    fn drop(&mut self) {
+        self.state.store(EMPTY, Ordering::Release);
+    }
+}
+
+pub(crate) struct OnceLock<T> {
+    state: AtomicU8,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+unsafe impl<T: Send + Sync> Sync for OnceLock<T> {}
+unsafe impl<T: Send> Send for OnceLock<T> {}
+
+impl<T> OnceLock<T> {
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU8::new(EMPTY),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    pub fn get(&self) -> Option<&T> {
+        if self.state.load(Ordering::Acquire) == INITIALIZED {
+            Some(unsafe { (*self.value.get()).assume_init_ref() })
+        } else {
+            None
+        }
+    }
+
+    #[inline(always)]
+    pub fn get_or_init<F>(&self, f: F) -> &T
+    where
+        F: FnOnce() -> T,
+    {
+        loop {
+            if let Some(value) = self.get() {
+                return value;
+            }
+
+            match self
+                .state
+                .compare_exchange(EMPTY, INITIALIZING, Ordering::Acquire, Ordering::Acquire)
+            {
+                Ok(_) => {
+                    #[cfg(panic = "unwind")]
+                    let guard = ResetOnUnwind {
+                        state: &self.state,
+                    };
+
+                    let value = f();
+
+                    #[cfg(panic = "unwind")]
+                    core::mem::forget(guard);
+
+                    unsafe {
+                        (*self.value.get()).write(value);
+                    }
+                    self.state.store(INITIALIZED, Ordering::Release);
+                    return unsafe { (*self.value.get()).assume_init_ref() };
+                }
+                Err(_) => {
+                    // Spin until initialized, or until the initializing
+                    // thread unwinds and rolls the state back to `EMPTY`, in
+                    // which case we loop around and race for the CAS
+                    // ourselves instead of recursing (no guaranteed TCO, and
+                    // this is exactly the flaky-initializer/embedded,
+                    // small-stack case this type needs to survive).
+                    let backoff = Backoff::new();
+                    loop {
+                        let state = self.state.load(Ordering::Acquire);
+                        if state == INITIALIZED {
+                            return unsafe { (*self.value.get()).assume_init_ref() };
+                        }
+                        if state == EMPTY {
+                            break;
+                        }
+                        backoff.snooze();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Like [`get_or_init`](Self::get_or_init), but the initializing closure
+    /// is allowed to fail.
+    ///
+    /// If `f` returns `Err`, the cell is left uninitialized (rolled back to
+    /// `EMPTY`) and the error is returned, so a later call can retry with a
+    /// different closure.
+    #[inline(always)]
+    pub fn get_or_try_init<F, E>(&self, f: F) -> Result<&T, E>
+    where
+        F: FnOnce() -> Result<T, E>,
+    {
+        loop {
+            if let Some(value) = self.get() {
+                return Ok(value);
+            }
+
+            match self
+                .state
+                .compare_exchange(EMPTY, INITIALIZING, Ordering::Acquire, Ordering::Acquire)
+            {
+                Ok(_) => {
+                    #[cfg(panic = "unwind")]
+                    let guard = ResetOnUnwind {
+                        state: &self.state,
+                    };
+
+                    let result = f();
+
+                    #[cfg(panic = "unwind")]
+                    core::mem::forget(guard);
+
+                    return match result {
+                        Ok(value) => {
+                            unsafe {
+                                (*self.value.get()).write(value);
+                            }
+                            self.state.store(INITIALIZED, Ordering::Release);
+                            Ok(unsafe { (*self.value.get()).assume_init_ref() })
+                        }
+                        Err(e) => {
+                            // Roll back so another caller can retry the
+                            // initialization instead of spinning forever.
+                            self.state.store(EMPTY, Ordering::Release);
+                            Err(e)
+                        }
+                    };
+                }
+                Err(_) => {
+                    // Spin until initialized, or until the initializing
+                    // thread rolls back to `EMPTY` (init failed), in which
+                    // case we loop around and race for the CAS ourselves.
+                    let backoff = Backoff::new();
+                    loop {
+                        let state = self.state.load(Ordering::Acquire);
+                        if state == INITIALIZED {
+                            return Ok(unsafe { (*self.value.get()).assume_init_ref() });
+                        }
+                        if state == EMPTY {
+                            break;
+                        }
+                        backoff.snooze();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Sets the contents of this cell to `value`.
+    ///
+    /// Returns `Ok(())` if the cell was empty and is now initialized, or
+    /// `Err(value)` giving the value back if the cell was already full.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        match self.try_insert(value) {
+            Ok(_) => Ok(()),
+            Err((_, value)) => Err(value),
+        }
+    }
+
+    /// Like [`set`](Self::set), but also returns a reference to the value
+    /// already in the cell on failure.
+    pub fn try_insert(&self, value: T) -> Result<&T, (&T, T)> {
+        let mut value = Some(value);
+        let result = self.get_or_init(|| value.take().unwrap());
+        match value {
+            None => Ok(result),
+            Some(value) => Err((result, value)),
+        }
+    }
+
+    /// Returns a mutable reference to the contained value, if initialized.
+    ///
+    /// Since this takes `&mut self`, no synchronization is needed.
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        if *self.state.get_mut() == INITIALIZED {
+            Some(unsafe { (*self.value.get()).assume_init_mut() })
+        } else {
+            None
+        }
+    }
+
+    /// Takes the value out of this cell, leaving it empty.
+    ///
+    /// Since this takes `&mut self`, no synchronization is needed, so this
+    /// can be used even if the cell is not `Sync` or `Send`.
+    pub fn take(&mut self) -> Option<T> {
+        if core::mem::replace(self.state.get_mut(), EMPTY) == INITIALIZED {
+            Some(unsafe { (*self.value.get()).assume_init_read() })
+        } else {
+            None
+        }
+    }
+
+    /// Consumes the cell, returning the wrapped value, if initialized.
+    pub fn into_inner(mut self) -> Option<T> {
+        self.take()
+    }
+}
+
+impl<T> Default for OnceLock<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for OnceLock<T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == INITIALIZED {
+            unsafe {
+                (*self.value.get()).assume_init_drop();
+            }
+        }
+    }
+}
@@ -0,0 +1,380 @@
+//! `std` backend: a combined state+wait-queue word so contended waiters park
+//! instead of spinning once an initializer runs long.
+//!
+//! The low two bits of `state_and_queue` hold the `EMPTY`/`INITIALIZING`/
+//! `INITIALIZED` state; while `INITIALIZING`, the remaining bits hold the
+//! head pointer of an intrusive list of stack-allocated [`Waiter`] nodes, one
+//! per parked thread. This mirrors the vendored `once_cell`'s `imp_std`.
+use crate::backoff::Backoff;
+use core::cell::{Cell, UnsafeCell};
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::thread::{self, Thread};
+
+const EMPTY: usize = 0b00;
+const INITIALIZING: usize = 0b01;
+const INITIALIZED: usize = 0b10;
+const STATE_MASK: usize = 0b11;
+
+/// Adaptive spin iterations tried before a contended thread registers as a
+/// waiter and parks, so short initializers never pay for a syscall.
+const SPIN_LIMIT: u32 = 6;
+
+/// Intrusive wait-queue node, stack-allocated by the parked thread itself and
+/// linked in via `state_and_queue`'s upper bits.
+struct Waiter {
+    thread: Cell<Option<Thread>>,
+    signaled: AtomicBool,
+    next: *mut Waiter,
+}
+
+pub(crate) struct OnceLock<T> {
+    state_and_queue: AtomicUsize,
+    value: UnsafeCell<MaybeUninit<T>>,
+}
+
+unsafe impl<T: Send + Sync> Sync for OnceLock<T> {}
+unsafe impl<T: Send> Send for OnceLock<T> {}
+
+impl<T> OnceLock<T> {
+    pub const fn new() -> Self {
+        Self {
+            state_and_queue: AtomicUsize::new(EMPTY),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+        }
+    }
+
+    pub fn get(&self) -> Option<&T> {
+        if self.state_and_queue.load(Ordering::Acquire) & STATE_MASK == INITIALIZED {
+            Some(unsafe { (*self.value.get()).assume_init_ref() })
+        } else {
+            None
+        }
+    }
+
+    #[inline(always)]
+    pub fn get_or_init<F>(&self, f: F) -> &T
+    where
+        F: FnOnce() -> T,
+    {
+        enum Void {}
+        match self.get_or_try_init(move || Ok::<T, Void>(f())) {
+            Ok(value) => value,
+            Err(void) => match void {},
+        }
+    }
+
+    /// Like [`get_or_init`](Self::get_or_init), but the initializing closure
+    /// is allowed to fail.
+    ///
+    /// If `f` returns `Err`, the cell is left uninitialized (rolled back to
+    /// `EMPTY`) and the error is returned, so a later call can retry with a
+    /// different closure.
+    #[inline(always)]
+    pub fn get_or_try_init<F, E>(&self, f: F) -> Result<&T, E>
+    where
+        F: FnOnce() -> Result<T, E>,
+    {
+        if let Some(value) = self.get() {
+            return Ok(value);
+        }
+        self.initialize(f)?;
+        Ok(unsafe { (*self.value.get()).assume_init_ref() })
+    }
+
+    /// Drives the CAS/park loop, writing into `self.value` through `f`
+    /// without monomorphizing the lock-free part of the algorithm per `T`.
+    fn initialize<F, E>(&self, f: F) -> Result<(), E>
+    where
+        F: FnOnce() -> Result<T, E>,
+    {
+        let mut f = Some(f);
+        let mut result: Result<(), E> = Ok(());
+        let slot = &self.value;
+
+        initialize_inner(&self.state_and_queue, &mut || match (f.take().unwrap())() {
+            Ok(value) => {
+                unsafe {
+                    (*slot.get()).write(value);
+                }
+                true
+            }
+            Err(e) => {
+                result = Err(e);
+                false
+            }
+        });
+        result
+    }
+
+    /// Sets the contents of this cell to `value`.
+    ///
+    /// Returns `Ok(())` if the cell was empty and is now initialized, or
+    /// `Err(value)` giving the value back if the cell was already full.
+    pub fn set(&self, value: T) -> Result<(), T> {
+        match self.try_insert(value) {
+            Ok(_) => Ok(()),
+            Err((_, value)) => Err(value),
+        }
+    }
+
+    /// Like [`set`](Self::set), but also returns a reference to the value
+    /// already in the cell on failure.
+    pub fn try_insert(&self, value: T) -> Result<&T, (&T, T)> {
+        let mut value = Some(value);
+        let result = self.get_or_init(|| value.take().unwrap());
+        match value {
+            None => Ok(result),
+            Some(value) => Err((result, value)),
+        }
+    }
+
+    /// Returns a mutable reference to the contained value, if initialized.
+    ///
+    /// Since this takes `&mut self`, no synchronization is needed.
+    pub fn get_mut(&mut self) -> Option<&mut T> {
+        if *self.state_and_queue.get_mut() & STATE_MASK == INITIALIZED {
+            Some(unsafe { (*self.value.get()).assume_init_mut() })
+        } else {
+            None
+        }
+    }
+
+    /// Takes the value out of this cell, leaving it empty.
+    ///
+    /// Since this takes `&mut self`, no synchronization is needed, so this
+    /// can be used even if the cell is not `Sync` or `Send`.
+    pub fn take(&mut self) -> Option<T> {
+        if core::mem::replace(self.state_and_queue.get_mut(), EMPTY) & STATE_MASK == INITIALIZED {
+            Some(unsafe { (*self.value.get()).assume_init_read() })
+        } else {
+            None
+        }
+    }
+
+    /// Consumes the cell, returning the wrapped value, if initialized.
+    pub fn into_inner(mut self) -> Option<T> {
+        self.take()
+    }
+}
+
+impl<T> Default for OnceLock<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for OnceLock<T> {
+    fn drop(&mut self) {
+        if *self.state_and_queue.get_mut() & STATE_MASK == INITIALIZED {
+            unsafe {
+                (*self.value.get()).assume_init_drop();
+            }
+        }
+    }
+}
+
+/// Runs `init` at most once, and only if this caller wins the
+/// `EMPTY -> INITIALIZING` race; otherwise parks until the winner finishes
+/// (successfully or not) and then returns or races again.
+///
+/// `init` writes the value itself and returns whether it succeeded, so this
+/// function is not generic over `T` or `E`.
+fn initialize_inner(state_and_queue: &AtomicUsize, init: &mut dyn FnMut() -> bool) {
+    loop {
+        let current = state_and_queue.load(Ordering::Acquire);
+        match current & STATE_MASK {
+            INITIALIZED => return,
+            EMPTY => {
+                if state_and_queue
+                    .compare_exchange_weak(
+                        current,
+                        INITIALIZING,
+                        Ordering::Acquire,
+                        Ordering::Acquire,
+                    )
+                    .is_err()
+                {
+                    continue;
+                }
+
+                // On unwind, roll back to `EMPTY` and wake every queued
+                // waiter so they can retry instead of parking forever on a
+                // slot that will never become `INITIALIZED`.
+                #[cfg(panic = "unwind")]
+                struct Guard<'a> {
+                    state_and_queue: &'a AtomicUsize,
+                }
+                #[cfg(panic = "unwind")]
+                impl Drop for Guard<'_> {
+                    fn drop(&mut self) {
+                        let previous = self.state_and_queue.swap(EMPTY, Ordering::AcqRel);
+                        wake_waiters(previous);
+                    }
+                }
+                #[cfg(panic = "unwind")]
+                let guard = Guard { state_and_queue };
+
+                let succeeded = init();
+
+                #[cfg(panic = "unwind")]
+                core::mem::forget(guard);
+
+                let final_state = if succeeded { INITIALIZED } else { EMPTY };
+                let previous = state_and_queue.swap(final_state, Ordering::AcqRel);
+                wake_waiters(previous);
+                return;
+            }
+            _ => {
+                debug_assert_eq!(current & STATE_MASK, INITIALIZING);
+                wait(state_and_queue, current);
+            }
+        }
+    }
+}
+
+/// Spins briefly, then registers the current thread as a [`Waiter`] and
+/// parks until the initializing thread wakes it, or returns immediately if
+/// the state has already left `INITIALIZING`.
+fn wait(state_and_queue: &AtomicUsize, mut current: usize) {
+    let backoff = Backoff::new();
+    for _ in 0..SPIN_LIMIT {
+        if current & STATE_MASK != INITIALIZING {
+            return;
+        }
+        backoff.snooze();
+        current = state_and_queue.load(Ordering::Acquire);
+    }
+
+    let mut node = Waiter {
+        thread: Cell::new(Some(thread::current())),
+        signaled: AtomicBool::new(false),
+        next: core::ptr::null_mut(),
+    };
+
+    loop {
+        if current & STATE_MASK != INITIALIZING {
+            return;
+        }
+
+        node.next = (current & !STATE_MASK) as *mut Waiter;
+        let me = &node as *const Waiter as usize;
+        debug_assert_eq!(me & STATE_MASK, 0, "Waiter must be at least 4-byte aligned");
+
+        // This CAS is on the same word that encodes the state, so it can
+        // only succeed while the cell is still `INITIALIZING`: if the
+        // winning thread finished in the meantime, `current` is stale and we
+        // loop back around to observe the new state directly, instead of
+        // ever leaving a dangling node linked into the queue.
+        match state_and_queue.compare_exchange_weak(
+            current,
+            (me & !STATE_MASK) | INITIALIZING,
+            Ordering::Release,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => break,
+            Err(new) => current = new,
+        }
+    }
+
+    // Linked into the queue: park until `wake_waiters` signals us.
+    while !node.signaled.load(Ordering::Acquire) {
+        thread::park();
+    }
+}
+
+/// Walks the intrusive list rooted at the (masked) pointer bits of a former
+/// `state_and_queue` value, waking every parked thread.
+///
+/// Reads each node's `next` and `thread` before signaling it, since the
+/// owning thread is free to unpark and drop its stack frame as soon as
+/// `signaled` is observed `true`.
+fn wake_waiters(state_and_queue: usize) {
+    let mut waiter = (state_and_queue & !STATE_MASK) as *mut Waiter;
+    while !waiter.is_null() {
+        let next = unsafe { (*waiter).next };
+        let thread = unsafe { (*waiter).thread.take() }.expect("waiter thread taken twice");
+        unsafe { (*waiter).signaled.store(true, Ordering::Release) };
+        thread.unpark();
+        waiter = next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn parked_waiter_is_woken_once_initializer_completes() {
+        let lock: Arc<OnceLock<u32>> = Arc::new(OnceLock::new());
+        let (started_tx, started_rx) = mpsc::channel::<()>();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+
+        let initializer = {
+            let lock = Arc::clone(&lock);
+            thread::spawn(move || {
+                *lock.get_or_init(|| {
+                    // Signal that we hold `INITIALIZING`, then block so the
+                    // waiter below has time to exhaust its short pre-park
+                    // spin and actually call `thread::park()`.
+                    started_tx.send(()).unwrap();
+                    release_rx.recv().unwrap();
+                    99
+                })
+            })
+        };
+        started_rx.recv().unwrap();
+
+        let waiter = {
+            let lock = Arc::clone(&lock);
+            thread::spawn(move || *lock.get_or_init(|| unreachable!("waiter must not initialize")))
+        };
+
+        // Comfortably longer than the few spin iterations `wait()` tries
+        // before parking, so the waiter is genuinely asleep in
+        // `thread::park()` when we unblock the initializer below.
+        thread::sleep(Duration::from_millis(200));
+        release_tx.send(()).unwrap();
+
+        assert_eq!(initializer.join().unwrap(), 99);
+        assert_eq!(waiter.join().unwrap(), 99);
+    }
+
+    #[test]
+    fn parked_waiter_is_woken_after_initializer_panics() {
+        let lock: Arc<OnceLock<u32>> = Arc::new(OnceLock::new());
+        let (started_tx, started_rx) = mpsc::channel::<()>();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+
+        let initializer = {
+            let lock = Arc::clone(&lock);
+            thread::spawn(move || {
+                lock.get_or_init(|| {
+                    started_tx.send(()).unwrap();
+                    release_rx.recv().unwrap();
+                    panic!("init failed");
+                });
+            })
+        };
+        started_rx.recv().unwrap();
+
+        let waiter = {
+            let lock = Arc::clone(&lock);
+            thread::spawn(move || *lock.get_or_init(|| 7))
+        };
+
+        // Give the waiter time to exhaust its pre-park spin and actually
+        // park before the initializer unwinds and rolls the state back to
+        // `EMPTY`, so this exercises the same wake path on the panic/rollback
+        // side, not just the success side.
+        thread::sleep(Duration::from_millis(200));
+        release_tx.send(()).unwrap();
+
+        assert!(initializer.join().is_err());
+        assert_eq!(waiter.join().unwrap(), 7);
+    }
+}
@@ -0,0 +1,88 @@
+//! A thread-safe cell which can be written to only once.
+//!
+//! It provides a way to initialize a value lazily and ensure that the
+//! initialization happens exactly once, even when accessed from multiple
+//! threads.
+//!
+//! Two backends are available: with the `std` feature enabled, contended
+//! waiters spin briefly and then park on an intrusive wait queue; under
+//! `no_std` they fall back to a pure spin/backoff loop, making this suitable
+//! for embedded systems and other constrained environments.
+#[cfg(feature = "std")]
+mod imp_std;
+
+#[cfg(not(feature = "std"))]
+mod imp_fallback;
+
+#[cfg(feature = "std")]
+pub(crate) use imp_std::OnceLock;
+
+#[cfg(not(feature = "std"))]
+pub(crate) use imp_fallback::OnceLock;
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    #[test]
+    fn panicking_initializer_does_not_deadlock_other_waiters() {
+        let lock: Arc<OnceLock<u32>> = Arc::new(OnceLock::new());
+
+        let panicking = {
+            let lock = Arc::clone(&lock);
+            thread::spawn(move || {
+                lock.get_or_init(|| panic!("init failed"));
+            })
+        };
+        assert!(panicking.join().is_err());
+
+        // The cell must have been rolled back to `EMPTY` instead of staying
+        // stuck at `INITIALIZING`, so this retry should succeed.
+        let value = lock.get_or_init(|| 42);
+        assert_eq!(*value, 42);
+    }
+
+    #[test]
+    fn concurrent_waiters_all_observe_the_same_value() {
+        let lock: Arc<OnceLock<u32>> = Arc::new(OnceLock::new());
+
+        let handles: std::vec::Vec<_> = (0..8)
+            .map(|_| {
+                let lock = Arc::clone(&lock);
+                thread::spawn(move || *lock.get_or_init(|| 7))
+            })
+            .collect();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), 7);
+        }
+    }
+
+    #[test]
+    fn set_and_try_insert_report_the_existing_value_on_conflict() {
+        let lock: OnceLock<u32> = OnceLock::new();
+
+        assert_eq!(lock.set(1), Ok(()));
+        assert_eq!(lock.set(2), Err(2));
+        assert_eq!(lock.try_insert(3), Err((&1, 3)));
+        assert_eq!(lock.get(), Some(&1));
+    }
+
+    #[test]
+    fn take_and_into_inner_empty_the_cell() {
+        let mut lock: OnceLock<u32> = OnceLock::new();
+        assert_eq!(lock.take(), None);
+
+        lock.set(5).unwrap();
+        assert_eq!(*lock.get_mut().unwrap(), 5);
+        assert_eq!(lock.take(), Some(5));
+        assert_eq!(lock.get(), None);
+
+        lock.set(6).unwrap();
+        assert_eq!(lock.into_inner(), Some(6));
+    }
+}